@@ -1,13 +1,18 @@
 //! ICMP "sockets".
 
-use core::future::poll_fn;
+use core::cell::Cell;
+use core::future::{poll_fn, Future};
+use core::marker::PhantomData;
 use core::mem;
 use core::task::{Context, Poll};
 
+use embassy_futures::select::{select, Either};
 use embassy_net_driver::Driver;
+use embassy_time::{Duration, Instant, Timer};
 use smoltcp::iface::{Interface, SocketHandle};
+use smoltcp::phy::ChecksumCapabilities;
 use smoltcp::socket::icmp::{self, Endpoint, PacketMetadata};
-use smoltcp::wire::IpAddress;
+use smoltcp::wire::{Icmpv4Message, Icmpv4Packet, Icmpv6Message, Icmpv6Packet, Icmpv6Repr, IpAddress, Ipv6Address};
 
 use crate::Stack;
 
@@ -19,13 +24,14 @@ pub enum RecvError {
     Exhausted,
     /// Provided received packet is not complete.
     Truncated,
+    /// The deadline elapsed before the operation completed.
+    TimedOut,
 }
 
 /// An ICMP socket.
 pub struct IcmpSocket<'a> {
     stack: Stack<'a>,
     handle: SocketHandle,
-    endpoint: Endpoint
 }
 
 impl<'a> IcmpSocket<'a> {
@@ -43,13 +49,20 @@ impl<'a> IcmpSocket<'a> {
             let rx_buffer: &'static mut [u8] = unsafe { mem::transmute(rx_buffer) };
             let tx_meta: &'static mut [PacketMetadata] = unsafe { mem::transmute(tx_meta) };
             let tx_buffer: &'static mut [u8] = unsafe { mem::transmute(tx_buffer) };
-            i.sockets.add(icmp::Socket::new(
+            let mut socket = icmp::Socket::new(
                 icmp::PacketBuffer::new(rx_meta, rx_buffer),
                 icmp::PacketBuffer::new(tx_meta, tx_buffer),
-            ))
+            );
+            // `Endpoint::Unspecified` is rejected by smoltcp's `bind` (it only accepts a
+            // concrete `Ident`/`Udp` endpoint); leave such a socket unbound rather than
+            // panicking, matching the historical behaviour of constructing one.
+            if endpoint.is_specified() {
+                socket.bind(endpoint).expect("binding a freshly created socket should not fail");
+            }
+            i.sockets.add(socket)
         });
 
-        Self { stack, handle, endpoint }
+        Self { stack, handle }
     }
 
     fn with_mut<R>(&self, f: impl FnOnce(&mut icmp::Socket, &mut Interface) -> R) -> R {
@@ -84,43 +97,89 @@ impl<'a> IcmpSocket<'a> {
         })
     }
 
-    /// Send a datagram.
+    /// Receive a datagram without copying it into a user-provided buffer.
+    ///
+    /// This method will wait until a datagram is received, then call `f` with a
+    /// reference to the packet's payload and its source address, consuming the
+    /// packet from the receive queue before `f` runs.
+    pub async fn recv_with<R>(&self, mut f: impl FnMut(&[u8], IpAddress) -> R) -> R {
+        poll_fn(move |cx| self.poll_recv_with(&mut f, cx)).await
+    }
+
+    /// Receive a datagram without copying it into a user-provided buffer.
+    ///
+    /// When no datagram is available, this method will return `Poll::Pending` and
+    /// register the current task to be notified when a datagram is received.
+    pub fn poll_recv_with<R>(&self, f: &mut impl FnMut(&[u8], IpAddress) -> R, cx: &mut Context<'_>) -> Poll<R> {
+        self.with_mut(|s, _| match s.recv() {
+            Ok((data, addr)) => Poll::Ready(f(data, addr)),
+            // `recv()` (unlike `recv_slice()`) never returns `Truncated`; no data is ready.
+            Err(icmp::RecvError::Truncated | icmp::RecvError::Exhausted) => {
+                s.register_recv_waker(cx.waker());
+                Poll::Pending
+            }
+        })
+    }
+
+    /// Peek at the next datagram without removing it from the receive queue.
     ///
-    /// This method will wait until the datagram has been sent.`
-    pub async fn send(&self, buf: &[u8]) {
-        poll_fn(move |cx| self.poll_send(buf, cx)).await
+    /// Returns `None` if no datagram is currently available.
+    pub fn peek_with<R>(&self, mut f: impl FnMut(&[u8], IpAddress) -> R) -> Option<R> {
+        self.with_mut(|s, _| s.peek().ok().map(|(data, addr)| f(data, *addr)))
     }
 
-    /// Send a datagram.
+    /// Send a datagram to the given address.
     ///
-    /// When the datagram has been sent, this method will return `Poll::Ready(Ok())`.
+    /// This method will wait until the datagram has been sent.
+    pub async fn send_to(&self, buf: &[u8], addr: IpAddress) -> Result<(), SendError> {
+        poll_fn(move |cx| self.poll_send_to(buf, addr, cx)).await
+    }
+
+    /// Send a datagram to the given address.
+    ///
+    /// When the datagram has been sent, this method will return `Poll::Ready(Ok(()))`.
     ///
     /// When the socket's send buffer is full, this method will return `Poll::Pending`
     /// and register the current task to be notified when the buffer has space available.
-    pub fn poll_send(&self, buf: &[u8], cx: &mut Context<'_>) -> Poll<()> {
-        if !self.endpoint.is_specified() {
-            return Poll::Pending; // TODO: definitely not
-        }
-        // where should this be sent, ident / vs option addr + port, ip v4/v6
-        let dst = match self.endpoint {
-            Endpoint::Unspecified => todo!(),
-            Endpoint::Ident(_) => todo!(),
-            Endpoint::Udp(listen_endpoint) => listen_endpoint.addr.expect("TODO - ident/none"),
-        };
-
-        self.with_mut(|s, _| match s.send_slice(buf, dst) {
+    pub fn poll_send_to(&self, buf: &[u8], addr: IpAddress, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        self.with_mut(|s, _| match s.send_slice(buf, addr) {
             // Entire datagram has been sent
-            Ok(()) => Poll::Ready(()),
+            Ok(()) => Poll::Ready(Ok(())),
             Err(icmp::SendError::BufferFull) => {
                 s.register_send_waker(cx.waker());
                 Poll::Pending
             }
-            Err(icmp::SendError::Unaddressable) => {
-                unimplemented!()
-            }
+            Err(icmp::SendError::Unaddressable) => Poll::Ready(Err(SendError::Unaddressable)),
         })
     }
 
+    /// Race `fut` against a `timeout`, returning [`RecvError::TimedOut`] if the deadline
+    /// elapses first.
+    async fn with_deadline<T>(&self, timeout: Duration, fut: impl Future<Output = T>) -> Result<T, RecvError> {
+        match select(fut, Timer::after(timeout)).await {
+            Either::First(res) => Ok(res),
+            Either::Second(_) => Err(RecvError::TimedOut),
+        }
+    }
+
+    /// Receive a datagram, or fail with [`RecvError::TimedOut`] if none arrives within `timeout`.
+    pub async fn recv_with_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(usize, IpAddress), RecvError> {
+        self.with_deadline(timeout, self.recv(buf)).await?
+    }
+
+    /// Send a datagram to the given address, or fail with [`SendError::TimedOut`] if the
+    /// send buffer doesn't free up within `timeout`.
+    pub async fn send_with_timeout(&self, buf: &[u8], addr: IpAddress, timeout: Duration) -> Result<(), SendError> {
+        match select(self.send_to(buf, addr), Timer::after(timeout)).await {
+            Either::First(res) => res,
+            Either::Second(_) => Err(SendError::TimedOut),
+        }
+    }
+
     /// Flush the socket.
     ///
     /// This method will wait until the socket is flushed.
@@ -137,6 +196,60 @@ impl<'a> IcmpSocket<'a> {
         })
         .await
     }
+
+    /// Split the socket into an owned sending half and an owned receiving half.
+    ///
+    /// The two halves share the same underlying socket, so one task can drive
+    /// [`IcmpReceiver::recv`] while another drives [`IcmpSender::send_to`], without a
+    /// `&mut` or a mutex. Both halves can be dropped independently and in any order;
+    /// the socket is removed from the stack once *both* have been dropped.
+    ///
+    /// `state` is caller-provided storage for the refcount shared by the two halves,
+    /// following the same pattern as the buffers passed to [`new`](Self::new); it must
+    /// outlive both halves. It's borrowed mutably (though the halves only ever read it
+    /// through a shared reference) so the borrow checker ties it up for as long as the
+    /// returned halves exist, making it impossible to pass the same `SplitState` to a
+    /// second `split` call while the first pair is still alive.
+    pub fn split(self, state: &'a mut SplitState) -> (IcmpSender<'a, 'a>, IcmpReceiver<'a, 'a>) {
+        state.refcount.set(2);
+        let state: &'a SplitState = &*state;
+        let (stack, handle) = (self.stack, self.handle);
+        mem::forget(self);
+        (
+            IcmpSender {
+                socket: SocketRef::Owned { stack, handle, state },
+                _borrow: PhantomData,
+            },
+            IcmpReceiver {
+                socket: SocketRef::Owned { stack, handle, state },
+                _borrow: PhantomData,
+            },
+        )
+    }
+
+    /// Split the socket into a borrowing sending half and a borrowing receiving half.
+    ///
+    /// Unlike [`split`](Self::split), neither half owns the socket: dropping them does
+    /// nothing, the socket is removed as usual when `self` is dropped, and the borrow
+    /// checker prevents either half from outliving `self`.
+    pub fn split_ref<'b>(&'b mut self) -> (IcmpSender<'a, 'b>, IcmpReceiver<'a, 'b>) {
+        (
+            IcmpSender {
+                socket: SocketRef::Borrowed {
+                    stack: self.stack,
+                    handle: self.handle,
+                },
+                _borrow: PhantomData,
+            },
+            IcmpReceiver {
+                socket: SocketRef::Borrowed {
+                    stack: self.stack,
+                    handle: self.handle,
+                },
+                _borrow: PhantomData,
+            },
+        )
+    }
 }
 
 impl Drop for IcmpSocket<'_> {
@@ -148,3 +261,436 @@ impl Drop for IcmpSocket<'_> {
 fn _assert_covariant<'a, 'b: 'a>(x: IcmpSocket<'b>) -> IcmpSocket<'a> {
     x
 }
+
+/// Caller-provided storage for the refcount shared by the two halves of a
+/// [`split`](IcmpSocket::split) socket. Avoids heap-allocating an `Rc`, keeping
+/// embassy-net alloc-free: callers that never call `split` pay nothing for it.
+pub struct SplitState {
+    refcount: Cell<u8>,
+}
+
+impl SplitState {
+    /// Create a new, empty split state to be passed to [`IcmpSocket::split`].
+    pub const fn new() -> Self {
+        Self { refcount: Cell::new(0) }
+    }
+}
+
+impl Default for SplitState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What an [`IcmpSender`]/[`IcmpReceiver`] half actually points at: either one of two
+/// jointly owned, refcounted halves (from [`IcmpSocket::split`]), or a plain borrow of
+/// a socket that still owns itself (from [`IcmpSocket::split_ref`]).
+enum SocketRef<'a> {
+    Owned {
+        stack: Stack<'a>,
+        handle: SocketHandle,
+        state: &'a SplitState,
+    },
+    Borrowed { stack: Stack<'a>, handle: SocketHandle },
+}
+
+impl<'a> SocketRef<'a> {
+    fn stack(&self) -> Stack<'a> {
+        match self {
+            SocketRef::Owned { stack, .. } => *stack,
+            SocketRef::Borrowed { stack, .. } => *stack,
+        }
+    }
+
+    fn handle(&self) -> SocketHandle {
+        match self {
+            SocketRef::Owned { handle, .. } => *handle,
+            SocketRef::Borrowed { handle, .. } => *handle,
+        }
+    }
+}
+
+impl Drop for SocketRef<'_> {
+    fn drop(&mut self) {
+        if let SocketRef::Owned { stack, handle, state } = self {
+            let remaining = state.refcount.get() - 1;
+            state.refcount.set(remaining);
+            if remaining == 0 {
+                stack.with_mut(|i| i.sockets.remove(*handle));
+            }
+        }
+    }
+}
+
+/// Error returned by [`IcmpSocket::send_to`]/[`IcmpSocket::send_with_timeout`] and
+/// [`IcmpSender::send_to`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SendError {
+    /// The destination address cannot be routed.
+    Unaddressable,
+    /// The deadline elapsed before the operation completed.
+    TimedOut,
+}
+
+/// The sending half of an [`IcmpSocket`], produced by [`IcmpSocket::split`] or
+/// [`IcmpSocket::split_ref`].
+///
+/// `'b` ties a `split_ref` half to the lifetime of the borrow it came from; halves
+/// from `split` are unconstrained (`'b` is just `'a`) since they own their socket.
+pub struct IcmpSender<'a, 'b> {
+    socket: SocketRef<'a>,
+    _borrow: PhantomData<&'b mut IcmpSocket<'a>>,
+}
+
+impl<'a> IcmpSender<'a, '_> {
+    fn with_mut<R>(&self, f: impl FnOnce(&mut icmp::Socket, &mut Interface) -> R) -> R {
+        let (stack, handle) = (self.socket.stack(), self.socket.handle());
+        stack.with_mut(|i| {
+            let socket = i.sockets.get_mut::<icmp::Socket>(handle);
+            let res = f(socket, &mut i.iface);
+            i.waker.wake();
+            res
+        })
+    }
+
+    /// Send a datagram to the given address.
+    ///
+    /// This method will wait until the datagram has been sent.
+    pub async fn send_to(&self, buf: &[u8], addr: IpAddress) -> Result<(), SendError> {
+        poll_fn(move |cx| self.poll_send_to(buf, addr, cx)).await
+    }
+
+    /// Send a datagram to the given address.
+    ///
+    /// When the datagram has been sent, this method will return `Poll::Ready(Ok(()))`.
+    ///
+    /// When the socket's send buffer is full, this method will return `Poll::Pending`
+    /// and register the current task to be notified when the buffer has space available.
+    pub fn poll_send_to(&self, buf: &[u8], addr: IpAddress, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        self.with_mut(|s, _| match s.send_slice(buf, addr) {
+            Ok(()) => Poll::Ready(Ok(())),
+            Err(icmp::SendError::BufferFull) => {
+                s.register_send_waker(cx.waker());
+                Poll::Pending
+            }
+            Err(icmp::SendError::Unaddressable) => Poll::Ready(Err(SendError::Unaddressable)),
+        })
+    }
+
+    /// Flush the socket.
+    ///
+    /// This method will wait until the socket is flushed.
+    pub async fn flush(&self) {
+        poll_fn(move |cx| {
+            self.with_mut(|s, _| {
+                if s.send_queue() == 0 {
+                    Poll::Ready(())
+                } else {
+                    s.register_send_waker(cx.waker());
+                    Poll::Pending
+                }
+            })
+        })
+        .await
+    }
+}
+
+/// The receiving half of an [`IcmpSocket`], produced by [`IcmpSocket::split`] or
+/// [`IcmpSocket::split_ref`].
+///
+/// `'b` ties a `split_ref` half to the lifetime of the borrow it came from; halves
+/// from `split` are unconstrained (`'b` is just `'a`) since they own their socket.
+pub struct IcmpReceiver<'a, 'b> {
+    socket: SocketRef<'a>,
+    _borrow: PhantomData<&'b mut IcmpSocket<'a>>,
+}
+
+impl<'a> IcmpReceiver<'a, '_> {
+    fn with_mut<R>(&self, f: impl FnOnce(&mut icmp::Socket, &mut Interface) -> R) -> R {
+        let (stack, handle) = (self.socket.stack(), self.socket.handle());
+        stack.with_mut(|i| {
+            let socket = i.sockets.get_mut::<icmp::Socket>(handle);
+            let res = f(socket, &mut i.iface);
+            i.waker.wake();
+            res
+        })
+    }
+
+    /// Receive a datagram.
+    ///
+    /// This method will wait until a datagram is received.
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<(usize, IpAddress), RecvError> {
+        poll_fn(move |cx| self.poll_recv(buf, cx)).await
+    }
+
+    /// Receive a datagram.
+    ///
+    /// When no datagram is available, this method will return `Poll::Pending` and
+    /// register the current task to be notified when a datagram is received.
+    pub fn poll_recv(&self, buf: &mut [u8], cx: &mut Context<'_>) -> Poll<Result<(usize, IpAddress), RecvError>> {
+        self.with_mut(|s, _| match s.recv_slice(buf) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(icmp::RecvError::Truncated) => Poll::Ready(Err(RecvError::Truncated)),
+            Err(icmp::RecvError::Exhausted) => {
+                s.register_recv_waker(cx.waker());
+                Poll::Pending
+            }
+        })
+    }
+}
+
+/// Error returned by [`Pinger::ping`].
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PingError {
+    /// No echo reply matching the request was received before the timeout elapsed.
+    TimedOut,
+    /// `addr` is unroutable: either the underlying socket rejected it as
+    /// unaddressable, or (IPv6 only) no source address could be chosen for it to
+    /// build the ICMPv6 checksum's pseudo-header.
+    NoRoute,
+    /// Another [`Pinger::ping`] (or [`Pinger::ping_stream`]) call is already waiting
+    /// for a reply on this `Pinger`.
+    ///
+    /// A `Pinger` demultiplexes replies by sequence number using a single receive
+    /// queue; a second call in flight at the same time would steal replies meant for
+    /// the first, so it's rejected instead of silently racing.
+    Busy,
+}
+
+/// Number of bytes of the echo payload used to carry the send timestamp.
+const ECHO_PAYLOAD_LEN: usize = 8;
+
+/// Default time to wait for an echo reply before giving up on an attempt.
+const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A high-level ICMP echo-request ("ping") client built on top of [`IcmpSocket`].
+///
+/// A `Pinger` owns a socket bound to a single 16-bit identifier and uses it to send
+/// echo requests and measure the round-trip time of their replies.
+pub struct Pinger<'a> {
+    socket: IcmpSocket<'a>,
+    ident: u16,
+    seq: Cell<u16>,
+    timeout: Duration,
+    busy: Cell<bool>,
+}
+
+/// Resets [`Pinger::busy`] when a [`Pinger::ping`] call finishes, errors out, or is
+/// cancelled (its future dropped) while waiting for a reply.
+struct BusyGuard<'a>(&'a Cell<bool>);
+
+impl Drop for BusyGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+impl<'a> Pinger<'a> {
+    /// Create a new `Pinger` bound to `ident`, using [`DEFAULT_PING_TIMEOUT`] for each attempt.
+    pub fn new<D: Driver>(
+        stack: Stack<'a>,
+        ident: u16,
+        rx_meta: &'a mut [PacketMetadata],
+        rx_buffer: &'a mut [u8],
+        tx_meta: &'a mut [PacketMetadata],
+        tx_buffer: &'a mut [u8],
+    ) -> Self {
+        Self::new_with_timeout::<D>(
+            stack,
+            ident,
+            DEFAULT_PING_TIMEOUT,
+            rx_meta,
+            rx_buffer,
+            tx_meta,
+            tx_buffer,
+        )
+    }
+
+    /// Create a new `Pinger` bound to `ident`, waiting up to `timeout` for each reply.
+    pub fn new_with_timeout<D: Driver>(
+        stack: Stack<'a>,
+        ident: u16,
+        timeout: Duration,
+        rx_meta: &'a mut [PacketMetadata],
+        rx_buffer: &'a mut [u8],
+        tx_meta: &'a mut [PacketMetadata],
+        tx_buffer: &'a mut [u8],
+    ) -> Self {
+        let socket = IcmpSocket::new::<D>(stack, Endpoint::Ident(ident), rx_meta, rx_buffer, tx_meta, tx_buffer);
+        Self {
+            socket,
+            ident,
+            seq: Cell::new(0),
+            timeout,
+            busy: Cell::new(false),
+        }
+    }
+
+    fn next_seq(&self) -> u16 {
+        let seq = self.seq.get();
+        self.seq.set(seq.wrapping_add(1));
+        seq
+    }
+
+    /// Send one ICMP echo request to `addr` and wait for the matching reply.
+    ///
+    /// Replies whose identifier or sequence number don't match this request are ignored.
+    /// Returns [`PingError::TimedOut`] if no matching reply arrives within this pinger's
+    /// timeout, [`PingError::NoRoute`] if `addr` is unroutable — either it's IPv6 and no
+    /// source address could be found for it, or the send itself was rejected as
+    /// unaddressable (e.g. an unspecified destination address) — or [`PingError::Busy`] if
+    /// another `ping`/`ping_stream` call on this `Pinger` is already waiting for a reply;
+    /// only one may be in flight at a time.
+    pub async fn ping(&self, addr: IpAddress) -> Result<Duration, PingError> {
+        if self.busy.replace(true) {
+            return Err(PingError::Busy);
+        }
+        let _guard = BusyGuard(&self.busy);
+
+        let seq = self.next_seq();
+        let sent = Instant::now();
+        let v6 = addr.is_ipv6();
+
+        let mut tx_buf = [0u8; ECHO_PAYLOAD_LEN + 8];
+        let request = if v6 {
+            // The ICMPv6 checksum covers a pseudo-header including the source address,
+            // which isn't known until the stack picks a route; an all-zero checksum is
+            // not a "skip verification" sentinel on this wire (unlike UDP) and is
+            // rejected on receive, so it must be computed here against the real address.
+            let src = match self.socket.with_mut(|_, iface| iface.get_source_address(&addr)) {
+                Some(IpAddress::Ipv6(src)) => src,
+                _ => return Err(PingError::NoRoute),
+            };
+            let IpAddress::Ipv6(dst) = addr else {
+                return Err(PingError::NoRoute);
+            };
+            build_echo_request_v6(&mut tx_buf, src, dst, self.ident, seq, sent)
+        } else {
+            build_echo_request_v4(&mut tx_buf, self.ident, seq, sent)
+        };
+        self.socket
+            .send_to(request, addr)
+            .await
+            .map_err(|_| PingError::NoRoute)?;
+
+        let ident = self.ident;
+        let wait_for_reply = async {
+            loop {
+                let matched = self
+                    .socket
+                    .recv_with(|data, _from| is_matching_echo_reply(data, v6, ident, seq))
+                    .await;
+                if matched {
+                    return;
+                }
+            }
+        };
+
+        match select(wait_for_reply, Timer::after(self.timeout)).await {
+            Either::First(()) => Ok(Instant::now() - sent),
+            Either::Second(_) => Err(PingError::TimedOut),
+        }
+    }
+
+    /// Ping `addr` repeatedly, waiting `interval` between each of `count` attempts.
+    ///
+    /// Like [`ping`](Self::ping), only one attempt may be in flight on this `Pinger` at
+    /// a time; driving two `PingStream`s (or a stream and a direct `ping` call) on the
+    /// same `Pinger` concurrently will surface [`PingError::Busy`] from the later one.
+    pub fn ping_stream(&self, addr: IpAddress, interval: Duration, count: usize) -> PingStream<'_, 'a> {
+        PingStream {
+            pinger: self,
+            addr,
+            interval,
+            remaining: count,
+        }
+    }
+}
+
+/// A stream of successive RTT measurements produced by [`Pinger::ping_stream`].
+pub struct PingStream<'s, 'a> {
+    pinger: &'s Pinger<'a>,
+    addr: IpAddress,
+    interval: Duration,
+    remaining: usize,
+}
+
+impl<'s, 'a> PingStream<'s, 'a> {
+    /// Wait for and return the next RTT measurement, or `None` once the requested
+    /// number of pings have been sent.
+    pub async fn next(&mut self) -> Option<Result<Duration, PingError>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let result = self.pinger.ping(self.addr).await;
+        if self.remaining > 0 {
+            Timer::after(self.interval).await;
+        }
+        Some(result)
+    }
+}
+
+/// Build an ICMPv4 echo request carrying `sent` as its payload, returning the portion
+/// of `buf` that was filled in.
+fn build_echo_request_v4(buf: &mut [u8], ident: u16, seq: u16, sent: Instant) -> &[u8] {
+    let payload = sent.as_ticks().to_be_bytes();
+    let len = 8 + payload.len();
+
+    let mut packet = Icmpv4Packet::new_unchecked(&mut buf[..len]);
+    packet.set_msg_type(Icmpv4Message::EchoRequest);
+    packet.set_msg_code(0);
+    packet.set_echo_ident(ident);
+    packet.set_echo_seq_no(seq);
+    packet.data_mut()[..payload.len()].copy_from_slice(&payload);
+    // The ICMPv4 checksum doesn't depend on a pseudo-header, so it can be computed here.
+    packet.fill_checksum();
+
+    &buf[..len]
+}
+
+/// Build an ICMPv6 echo request carrying `sent` as its payload, returning the portion
+/// of `buf` that was filled in.
+///
+/// Unlike ICMPv4, the checksum covers a pseudo-header built from `src`/`dst`, so both
+/// must be known (e.g. via [`Interface::get_source_address`]) before this can run; see
+/// `smoltcp`'s own `examples/ping.rs`.
+fn build_echo_request_v6(
+    buf: &mut [u8],
+    src: Ipv6Address,
+    dst: Ipv6Address,
+    ident: u16,
+    seq: u16,
+    sent: Instant,
+) -> &[u8] {
+    let payload = sent.as_ticks().to_be_bytes();
+    let repr = Icmpv6Repr::EchoRequest {
+        ident,
+        seq_no: seq,
+        data: &payload,
+    };
+    let len = repr.buffer_len();
+
+    let mut packet = Icmpv6Packet::new_unchecked(&mut buf[..len]);
+    repr.emit(&src, &dst, &mut packet, &ChecksumCapabilities::default());
+
+    &buf[..len]
+}
+
+/// Check whether `data` is an echo reply matching the given identifier and sequence number.
+fn is_matching_echo_reply(data: &[u8], v6: bool, ident: u16, seq: u16) -> bool {
+    if v6 {
+        let Ok(packet) = Icmpv6Packet::new_checked(data) else {
+            return false;
+        };
+        packet.msg_type() == Icmpv6Message::EchoReply && packet.echo_ident() == ident && packet.echo_seq_no() == seq
+    } else {
+        let Ok(packet) = Icmpv4Packet::new_checked(data) else {
+            return false;
+        };
+        packet.msg_type() == Icmpv4Message::EchoReply && packet.echo_ident() == ident && packet.echo_seq_no() == seq
+    }
+}